@@ -1,12 +1,70 @@
+use std::cell::Cell;
 use std::collections::HashMap;
-use std::env;
 use std::fs;
 use std::path::PathBuf;
-use std::io::{self, BufRead};
+use std::io::{self, BufRead, Write};
 use std::path::Path;
 use std::vec;
+use std::time::{SystemTime, UNIX_EPOCH};
+use clap::{Parser, Subcommand};
 use cliclack::{select, intro};
 use console::style;
+use serde::Deserialize;
+
+const DEFAULT_BASHRC: &str = ".bashrc";
+const DEFAULT_ALIAS_DIR: &str = "aliases";
+const DEFAULT_SKEL: &str = "/etc/skel/.bashrc";
+const DEFAULT_GROUP: &str = "default";
+
+/// Manage modular aliases in your `.bashrc`.
+#[derive(Parser)]
+#[command(name = "dotbasher", about = "Manage modular aliases in your .bashrc")]
+struct Cli {
+    /// Path to the .bashrc file to manage.
+    #[arg(long, default_value = DEFAULT_BASHRC, global = true)]
+    bashrc: PathBuf,
+
+    /// Directory containing alias files and manifests.
+    #[arg(long = "alias-dir", default_value = DEFAULT_ALIAS_DIR, global = true)]
+    alias_dir: PathBuf,
+
+    /// Base template used to seed .bashrc when it doesn't exist yet.
+    #[arg(long, default_value = DEFAULT_SKEL, global = true)]
+    skel: PathBuf,
+
+    /// Accept all new aliases without prompting.
+    #[arg(short = 'y', long = "auto-confirm", global = true)]
+    auto_confirm: bool,
+
+    /// Render a unified diff instead of writing .bashrc.
+    #[arg(long = "dry-run", global = true)]
+    dry_run: bool,
+
+    /// Restrict a build to a single alias group.
+    #[arg(long, global = true)]
+    only: Option<String>,
+
+    /// Skip an alias group during a build.
+    #[arg(long, global = true)]
+    skip: Option<String>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Rebuild the modular alias blocks in .bashrc (default).
+    Build,
+    /// Remove all modular alias blocks from .bashrc.
+    Remove,
+    /// Restore .bashrc from its most recent backup.
+    Restore,
+    /// Print every resolved alias and the file it came from.
+    List,
+    /// Validate include references and alias syntax.
+    Check,
+}
 
 #[derive(PartialEq)]
 enum ConflictType {
@@ -33,114 +91,609 @@ impl AliasSource {
 struct Alias {
     value: String,
     source: AliasSource,
+    group: Option<String>,
+    /// Positional parameter names if this is a parameterized alias; such
+    /// aliases render as a `name() { ... }` shell function instead of `alias`.
+    params: Option<Vec<String>>,
+    /// Human-readable blurb carried by manifest entries (e.g. `description:`
+    /// in a YAML/TOML manifest); bare `alias name=value` lines never have one.
+    description: Option<String>,
+}
+
+/// A single entry in a YAML/TOML alias manifest: either a bare value
+/// (`ll: "ls -la"`), a richer object carrying optional metadata, or a
+/// parameterized entry (`{ args: [branch], body: "git checkout $branch" }`).
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ManifestAliasEntry {
+    Simple(String),
+    Detailed {
+        #[serde(default)]
+        value: Option<String>,
+        #[serde(default)]
+        body: Option<String>,
+        #[serde(default)]
+        args: Option<Vec<String>>,
+        #[serde(default)]
+        group: Option<String>,
+        #[serde(default)]
+        description: Option<String>,
+    },
+}
+
+/// Resolved paths and mutable conflict-resolution state for a single run.
+/// Threading this through replaces the old hardcoded `BASHRC`/`ALIAS_DIR`
+/// constants and `static mut` flags, so the tool can be pointed at other
+/// locations (e.g. a `.zshrc`, or test fixtures) and invoked programmatically.
+struct Config {
+    bashrc: PathBuf,
+    alias_dir: PathBuf,
+    skel: PathBuf,
+    only_group: Option<String>,
+    skip_groups: Vec<String>,
+    yolo_mode: Cell<bool>,
+    ignore_future_conflicts: Cell<bool>,
+    accept_all_new: Cell<bool>,
+}
+
+impl Config {
+    fn from_cli(cli: &Cli) -> Config {
+        Config {
+            bashrc: cli.bashrc.clone(),
+            alias_dir: cli.alias_dir.clone(),
+            skel: cli.skel.clone(),
+            only_group: cli.only.clone(),
+            skip_groups: cli.skip.clone().into_iter().collect(),
+            yolo_mode: Cell::new(cli.auto_confirm),
+            ignore_future_conflicts: Cell::new(false),
+            accept_all_new: Cell::new(false),
+        }
+    }
+
+    fn bashrc_str(&self) -> &str {
+        self.bashrc.to_str().expect("Failed to fail properly, what a fail.")
+    }
+}
+
+/// Start marker for a single group's delimited block, e.g. `[git]`.
+fn group_start_marker(group: &str) -> String {
+    format!("#--- BEGIN Modular Aliases [{}] ---", group)
+}
+
+/// End marker for a single group's delimited block.
+fn group_end_marker(group: &str) -> String {
+    format!("#--- END Modular Aliases [{}] ---", group)
+}
+
+/// Finds every existing group block in `content`, keyed by group name.
+fn extract_group_sections(content: &str) -> HashMap<String, String> {
+    let start_prefix = "#--- BEGIN Modular Aliases [";
+    let mut sections = HashMap::new();
+    let mut search_from = 0;
+
+    while let Some(rel_start) = content[search_from..].find(start_prefix) {
+        let start = search_from + rel_start;
+        let name_start = start + start_prefix.len();
+        let Some(rel_name_end) = content[name_start..].find("] ---") else { break };
+        let group_name = content[name_start..name_start + rel_name_end].to_string();
+        let end_marker = group_end_marker(&group_name);
+
+        match content[start..].find(&end_marker) {
+            Some(rel_end) => {
+                let end = start + rel_end + end_marker.len();
+                sections.insert(group_name, content[start..end].to_string());
+                search_from = end;
+            },
+            None => break,
+        }
+    }
+
+    sections
+}
+
+/// Recursively collects alias files under `dir`, pairing each with the group
+/// it belongs to: `None` for files directly in `aliases/`, or the name of the
+/// immediate subdirectory of `aliases/` the file was found under.
+fn collect_alias_files(dir: &Path, group: Option<&str>) -> io::Result<Vec<(PathBuf, Option<String>)>> {
+    let mut files = Vec::new();
+    let mut entries: Vec<_> = fs::read_dir(dir)?
+        .map(|res| res.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, std::io::Error>>()?;
+    entries.sort();
+
+    for entry in entries {
+        if entry.is_dir() {
+            let sub_group = match group {
+                Some(g) => g.to_string(),
+                None => entry.file_name().and_then(|n| n.to_str()).unwrap_or(DEFAULT_GROUP).to_string(),
+            };
+            files.extend(collect_alias_files(&entry, Some(&sub_group))?);
+        } else if entry.is_file() {
+            files.push((entry, group.map(|g| g.to_string())));
+        }
+    }
+
+    Ok(files)
 }
 
-static mut IGNORE_FUTURE_CONFLICTS: bool = false;
-static mut ACCEPT_ALL_NEW: bool = false;
-static mut YOLO_MODE: bool = false;
+/// Copies `path` to a `.bak.<epoch>` sibling and returns the backup's path.
+fn create_backup(path: &str) -> io::Result<PathBuf> {
+    let epoch = SystemTime::now().duration_since(UNIX_EPOCH).expect("Failed to fail properly, what a fail.").as_secs();
+    let backup_path = PathBuf::from(format!("{}.bak.{}", path, epoch));
+    fs::copy(path, &backup_path)?;
+    Ok(backup_path)
+}
+
+/// Writes `content` to `path` by writing a temp file in the same directory,
+/// fsyncing it, then atomically renaming it over `path`.
+fn write_atomic(path: &str, content: &str) -> io::Result<()> {
+    let target = Path::new(path);
+    let dir = target.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or_else(|| Path::new("."));
+    let file_name = target.file_name().and_then(|n| n.to_str()).unwrap_or("bashrc");
+    let tmp_path = dir.join(format!(".{}.tmp", file_name));
+
+    let mut tmp_file = fs::File::create(&tmp_path)?;
+    tmp_file.write_all(content.as_bytes())?;
+    tmp_file.sync_all()?;
+    drop(tmp_file);
+
+    fs::rename(&tmp_path, target)
+}
+
+/// Finds the most recently created `.bak.<epoch>` backup for `path`, if any.
+fn find_latest_backup(path: &str) -> Option<PathBuf> {
+    let target = Path::new(path);
+    let dir = target.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or_else(|| Path::new("."));
+    let file_name = target.file_name()?.to_str()?.to_string();
+    let prefix = format!("{}.bak.", file_name);
+
+    let mut backups: Vec<(u64, PathBuf)> = fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_str()?.to_string();
+            let epoch: u64 = name.strip_prefix(&prefix)?.parse().ok()?;
+            Some((epoch, entry.path()))
+        })
+        .collect();
+
+    backups.sort_by_key(|(epoch, _)| *epoch);
+    backups.pop().map(|(_, backup_path)| backup_path)
+}
+
+/// Restores `bashrc` from `backup_path`, if one was taken.
+fn restore_backup(bashrc: &str, backup_path: &Option<PathBuf>) {
+    if let Some(backup_path) = backup_path {
+        fs::copy(backup_path, bashrc).expect("Failed to restore backup after failed build.");
+    }
+}
+
+/// Walks `config.alias_dir`, parsing every alias file/manifest found into a
+/// single map, applying the same conflict resolution as a monolithic file would.
+fn load_incoming_aliases(config: &Config) -> io::Result<HashMap<String, Alias>> {
+    let mut incoming_aliases: HashMap<String, Alias> = HashMap::new();
+    let mut referenced_include_file_paths: Vec<String> = vec![];
+
+    for (path, group) in collect_alias_files(&config.alias_dir, None)? {
+        let file_path = path.to_str().expect("Failed to fail properly, what a fail.");
+        process_alias_file(file_path, &mut incoming_aliases, &mut referenced_include_file_paths, group.as_deref(), config).expect("Failed to fail properly, what a fail.");
+    }
+
+    Ok(incoming_aliases)
+}
+
+/// Parses every existing group block out of a `.bashrc`'s contents.
+fn load_existing_aliases(base_content: &str) -> HashMap<String, Alias> {
+    let mut existing_aliases: HashMap<String, Alias> = HashMap::new();
+    for (group_name, section) in extract_group_sections(base_content) {
+        for (alias_name, mut alias) in parse_modular_aliases(&section, AliasSource::Default) {
+            alias.group = Some(group_name.clone());
+            existing_aliases.insert(alias_name, alias);
+        }
+    }
+    existing_aliases
+}
+
+/// Renders one alias as either a plain `alias name=value` line or, for a
+/// parameterized alias, a `name() { ... }` shell function that binds each
+/// declared parameter to its positional argument by name before running the body.
+fn render_alias_line(alias_name: &str, alias: &Alias) -> String {
+    match &alias.params {
+        Some(params) => {
+            let mut function = format!("{}() {{\n", alias_name);
+            for (index, param) in params.iter().enumerate() {
+                function.push_str(&format!("    local {}=\"${}\"\n", param, index + 1));
+            }
+            function.push_str(&format!("    {}\n", alias.value));
+            function.push_str("}\n");
+            function
+        },
+        None => format!("alias {}={}\n", alias_name, alias.value),
+    }
+}
+
+/// Returns whether `group_name` is in scope for this run, honoring
+/// `config.only_group`/`config.skip_groups`.
+fn group_in_scope(group_name: &str, config: &Config) -> bool {
+    if let Some(only) = &config.only_group {
+        if group_name != only {
+            return false;
+        }
+    }
+    !config.skip_groups.iter().any(|g| g == group_name)
+}
+
+/// Drops any incoming alias whose group is out of scope for this run, so an
+/// `--only`/`--skip` build never merges aliases it has no intention of writing
+/// back out into the existing `.bashrc` set. Conflicts between incoming files
+/// are still resolved while loading, before this filter runs.
+fn filter_aliases_by_group(aliases: HashMap<String, Alias>, config: &Config) -> HashMap<String, Alias> {
+    aliases
+        .into_iter()
+        .filter(|(_, alias)| {
+            let group_name = alias.group.clone().unwrap_or_else(|| DEFAULT_GROUP.to_string());
+            group_in_scope(&group_name, config)
+        })
+        .collect()
+}
+
+/// Renders `aliases` into `base_content`: each affected group's own delimited
+/// block (honoring `config.only_group`/`config.skip_groups`) is removed and the
+/// rebuilt block is appended at the end of the file; every other part of the
+/// file is left untouched.
+fn render_bashrc(base_content: &str, aliases: &HashMap<String, Alias>, config: &Config) -> String {
+    let mut aliases_by_group: HashMap<String, Vec<String>> = HashMap::new();
+    for (alias_name, alias) in aliases {
+        let group_name = alias.group.clone().unwrap_or_else(|| DEFAULT_GROUP.to_string());
+        aliases_by_group.entry(group_name).or_default().push(alias_name.clone());
+    }
+
+    let mut group_names: Vec<String> = aliases_by_group.keys().cloned().collect();
+    group_names.sort();
+
+    let mut final_bashrc = base_content.to_string();
+    for group_name in &group_names {
+        if !group_in_scope(group_name, config) {
+            continue;
+        }
+
+        let mut alias_names = aliases_by_group[group_name].clone();
+        alias_names.sort();
+
+        let mut block = String::new();
+        block.push_str(&format!("{}\n", group_start_marker(group_name)));
+        for alias_name in alias_names {
+            block.push_str(&render_alias_line(&alias_name, aliases.get(&alias_name).unwrap()));
+        }
+        block.push_str(&format!("{}\n", group_end_marker(group_name)));
+
+        // Rebuilding a group only rewrites that group's own block.
+        final_bashrc = remove_section(&final_bashrc, &group_start_marker(group_name), &group_end_marker(group_name));
+        final_bashrc.push_str(&block);
+    }
 
-const ALIAS_DIR: &str = "aliases";
-const BASHRC: &str = ".bashrc";
-const BASE_BASHRC: &str = "/etc/skel/.bashrc";
-const MODULAR_ALIAS_START_MARKER: &str = "#--- BEGIN Modular Aliases [Block's contents will be replaced on build] ---";
-const MODULAR_ALIAS_END_MARKER: &str = "#--- END Modular Aliases ---";
+    final_bashrc
+}
 
 fn main() -> io::Result<()> {
-    // Parse command-line arguments.
-    let args: Vec<String> = env::args().collect();
-    let auto_confirm = args.iter().any(|arg| arg == "--auto-confirm" || arg == "-y");
-    let remove_aliases = args.iter().any(|arg| arg == "--remove-aliases");
-    let mut bashrc_file_exists = false;
+    let cli = Cli::parse();
+    let config = Config::from_cli(&cli);
+    let dry_run = cli.dry_run;
+
+    match cli.command.unwrap_or(Command::Build) {
+        Command::Build => run_build(&config, dry_run),
+        Command::Remove => run_remove(&config),
+        Command::Restore => run_restore(&config),
+        Command::List => run_list(&config),
+        Command::Check => run_check(&config),
+    }
+}
+
+/// Rebuilds the modular alias blocks in `.bashrc` from the configured alias directory.
+fn run_build(config: &Config, dry_run: bool) -> io::Result<()> {
+    let bashrc = config.bashrc_str();
 
     // Load existing .bashrc content (use base template if not present).
-    let bashrc_path = Path::new(BASHRC);
-    let base_content = if bashrc_path.exists() {
+    let mut bashrc_file_exists = false;
+    let base_content = if config.bashrc.exists() {
         bashrc_file_exists = true;
-        fs::read_to_string(BASHRC).expect("Failed to fail properly, what a fail.")
+        fs::read_to_string(bashrc).expect("Failed to fail properly, what a fail.")
     } else {
-        println!(".bashrc not found. Using base template from {}...", BASE_BASHRC);
-        fs::read_to_string(BASE_BASHRC).expect("Failed to fail properly, what a fail.")
+        println!(".bashrc not found. Using base template from {}...", config.skel.display());
+        fs::read_to_string(&config.skel).expect("Failed to fail properly, what a fail.")
     };
 
-    if bashrc_file_exists && remove_aliases {
-        println!("Removing existing modular aliases...");
-        let new_bashrc = remove_section(&base_content, MODULAR_ALIAS_START_MARKER, MODULAR_ALIAS_END_MARKER);
-        fs::write(BASHRC, new_bashrc).expect("Failed to fail properly, what a fail.");
-    
-        return Ok(());
+    // Back up the current .bashrc before touching it, so a failed build can be rolled back.
+    // Dry runs never write, so there's nothing to back up.
+    let backup_path = if bashrc_file_exists && !dry_run {
+        Some(create_backup(bashrc).expect("Failed to fail properly, what a fail."))
+    } else {
+        None
     };
 
-    unsafe {
-        YOLO_MODE = auto_confirm;
+    let build = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| -> io::Result<()> {
+        if !config.alias_dir.exists() {
+            eprintln!("Error: '{}' directory not found. Please create it.", config.alias_dir.display());
+            std::process::exit(1);
+        }
+
+        let existing_aliases = load_existing_aliases(&base_content);
+        let incoming_aliases = load_incoming_aliases(config)?;
+
+        // Resolve `@name` alias-to-alias references before diffing against what's
+        // already on disk — `existing_aliases` comes back from .bashrc already resolved,
+        // so comparing it against still-unresolved incoming values would flag every
+        // `@ref`-using alias as "changed" on every single rebuild.
+        let incoming_aliases = resolve_alias_references(&incoming_aliases)?;
+
+        // Drop out-of-scope groups before merging, so an `--only`/`--skip` build never
+        // prompts for (or panics on) conflicts against existing .bashrc aliases in
+        // groups it won't write back out.
+        let incoming_aliases = filter_aliases_by_group(incoming_aliases, config);
+
+        // Merge incoming aliases with existing ones.
+        let new_aliases_from_file = compile_new_aliases(&existing_aliases, &incoming_aliases, config).expect("Failed to fail properly, what a fail.");
+
+        let final_bashrc = render_bashrc(&base_content, &new_aliases_from_file, config);
+
+        if dry_run {
+            let diff = render_unified_diff(bashrc, &format!("{} (proposed)", bashrc), &base_content, &final_bashrc, 3);
+            if diff.is_empty() {
+                println!("No changes.");
+            } else {
+                print!("{}", diff);
+            }
+            return Ok(());
+        }
+
+        write_atomic(bashrc, &final_bashrc)
+    }));
+
+    match build {
+        Ok(Ok(())) => {
+            if !dry_run {
+                println!("Modular alias setup complete. Remember to source your .bashrc (e.g., 'source ~/.bashrc') to apply the changes.");
+            }
+            Ok(())
+        },
+        Ok(Err(e)) => {
+            restore_backup(bashrc, &backup_path);
+            Err(e)
+        },
+        Err(_panic) => {
+            restore_backup(bashrc, &backup_path);
+            if backup_path.is_some() {
+                eprintln!("{}", style("Error: build failed; restored your previous .bashrc from backup.").red());
+            } else {
+                eprintln!("{}", style("Error: build failed; .bashrc was not written.").red());
+            }
+            std::process::exit(1);
+        }
     }
+}
 
-    // Check aliases directory exists.
-    if !Path::new(ALIAS_DIR).exists() {
-        eprintln!("Error: '{}' directory not found. Please create it.", ALIAS_DIR);
-        std::process::exit(1);
+/// Removes every modular alias block from `.bashrc`, leaving the rest of the file alone.
+fn run_remove(config: &Config) -> io::Result<()> {
+    let bashrc = config.bashrc_str();
+    if !config.bashrc.exists() {
+        println!("'{}' does not exist; nothing to remove.", bashrc);
+        return Ok(());
     }
 
-    // Parse any existing modular aliases from .bashrc.
-    let existing_aliases: HashMap<String, Alias> = if let Some(existing_section) = extract_section(&base_content, MODULAR_ALIAS_START_MARKER, MODULAR_ALIAS_END_MARKER) {
-        parse_modular_aliases(existing_section, AliasSource::Default)
+    println!("Removing existing modular aliases...");
+    let base_content = fs::read_to_string(bashrc).expect("Failed to fail properly, what a fail.");
+    let mut new_bashrc = base_content.clone();
+    for group_name in extract_group_sections(&base_content).keys() {
+        new_bashrc = remove_section(&new_bashrc, &group_start_marker(group_name), &group_end_marker(group_name));
+    }
+
+    let backup_path = create_backup(bashrc).expect("Failed to fail properly, what a fail.");
+    if let Err(e) = write_atomic(bashrc, &new_bashrc) {
+        restore_backup(bashrc, &Some(backup_path));
+        return Err(e);
+    }
+
+    Ok(())
+}
+
+/// Restores `.bashrc` from its most recently created backup.
+fn run_restore(config: &Config) -> io::Result<()> {
+    let bashrc = config.bashrc_str();
+    match find_latest_backup(bashrc) {
+        Some(backup_path) => {
+            fs::copy(&backup_path, bashrc).expect("Failed to fail properly, what a fail.");
+            println!("Restored {} from {}.", bashrc, backup_path.display());
+            Ok(())
+        },
+        None => {
+            eprintln!("Error: no backup found for '{}'.", bashrc);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Prints every alias that a build would resolve to, alongside the file it came from.
+fn run_list(config: &Config) -> io::Result<()> {
+    let base_content = if config.bashrc.exists() {
+        fs::read_to_string(config.bashrc_str()).expect("Failed to fail properly, what a fail.")
     } else {
-        HashMap::new()
+        String::new()
     };
 
-    // Prepare a hash map for incoming alias definitions.
-    let mut incoming_aliases: HashMap<String, Alias> = HashMap::new();
-    
-    // Prepare a hash map for referenced include file paths.
-    let mut referenced_include_file_paths: Vec<String> = vec![];
+    if !config.alias_dir.exists() {
+        eprintln!("Error: '{}' directory not found. Please create it.", config.alias_dir.display());
+        std::process::exit(1);
+    }
 
-    // Process additional alias files in the aliases directory.
-    let mut entries: Vec<_> = fs::read_dir(PathBuf::from(ALIAS_DIR))?
-        .map(|res| res.map(|entry| entry.path()))
-        .collect::<Result<Vec<_>, std::io::Error>>()?;
+    let existing_aliases = load_existing_aliases(&base_content);
+    let incoming_aliases = load_incoming_aliases(config)?;
+    let incoming_aliases = resolve_alias_references(&incoming_aliases)?;
+    let incoming_aliases = filter_aliases_by_group(incoming_aliases, config);
+    let aliases = compile_new_aliases(&existing_aliases, &incoming_aliases, config).expect("Failed to fail properly, what a fail.");
 
-    entries.sort();
+    let mut alias_names: Vec<&String> = aliases.keys().collect();
+    alias_names.sort();
 
+    for alias_name in alias_names {
+        let alias = aliases.get(alias_name).unwrap();
+        let group_name = alias.group.clone().unwrap_or_else(|| DEFAULT_GROUP.to_string());
+        println!("{} = {} [{}] (from {})", style(alias_name).cyan(), alias.value, group_name, alias.source.get_path());
+        if let Some(description) = &alias.description {
+            println!("    {}", style(description).dim());
+        }
+    }
 
-    // alias_files.sort_by(|a, b| a.cmp(&b));
-    for entry in entries {
-        let path = entry.as_path();
-        if path.is_file() {
-            let file_path = path.to_str().expect("Failed to fail properly, what a fail.");
-            if let Some(filename) = path.file_name().and_then(|n| n.to_str()) {
-                if filename == env::args().next().unwrap_or_default() {
-                    continue;
-                }
-                process_alias_file(file_path, &mut incoming_aliases, &mut referenced_include_file_paths).expect("Failed to fail properly, what a fail.");
-            }
+    Ok(())
+}
+
+/// Validates every alias file/manifest and its `#include:` references without writing
+/// anything; exits non-zero if any problem is found.
+fn run_check(config: &Config) -> io::Result<()> {
+    if !config.alias_dir.exists() {
+        eprintln!("Error: '{}' directory not found. Please create it.", config.alias_dir.display());
+        std::process::exit(1);
+    }
+
+    let mut problems: Vec<String> = Vec::new();
+    for (path, _group) in collect_alias_files(&config.alias_dir, None)? {
+        let file_path = path.to_str().expect("Failed to fail properly, what a fail.");
+        check_alias_file(file_path, config, &mut problems);
+    }
+
+    // Also make sure every `@name` reference resolves, the same way a real build
+    // would. Conflict resolution doesn't matter here since we only care about the
+    // final set of values, so force yolo mode to avoid prompting during a check.
+    let check_config = Config {
+        bashrc: config.bashrc.clone(),
+        alias_dir: config.alias_dir.clone(),
+        skel: config.skel.clone(),
+        only_group: config.only_group.clone(),
+        skip_groups: config.skip_groups.clone(),
+        yolo_mode: Cell::new(true),
+        ignore_future_conflicts: Cell::new(false),
+        accept_all_new: Cell::new(false),
+    };
+    // Per-file checks above already report malformed lines individually and may
+    // have left invalid aliases out of the map entirely, so guard against a
+    // stray panic bubbling up from loading/resolving the rest.
+    let reference_check = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        load_incoming_aliases(&check_config).and_then(|aliases| resolve_alias_references(&aliases))
+    }));
+    match reference_check {
+        Ok(Ok(_)) => {},
+        Ok(Err(e)) => problems.push(e.to_string()),
+        Err(_panic) => problems.push("failed to validate @name references (see errors above)".to_string()),
+    }
+
+    if problems.is_empty() {
+        println!("{}", style("OK: all alias files and includes are valid.").green());
+        Ok(())
+    } else {
+        for problem in &problems {
+            eprintln!("{} {}", style("Error:").red(), problem);
         }
+        std::process::exit(1);
     }
+}
 
-    // Merge incoming aliases with existing ones (if not removing).
-    let new_aliases_from_file = compile_new_aliases(&existing_aliases, &incoming_aliases).expect("Failed to fail properly, what a fail.");
+/// Checks a single alias file/manifest for syntax errors and dangling `#include:` references.
+fn check_alias_file(path: &str, config: &Config, problems: &mut Vec<String>) {
+    if path.ends_with(".yml") || path.ends_with(".yaml") || path.ends_with(".toml") {
+        if let Err(e) = parse_manifest_file(path) {
+            problems.push(format!("{}: failed to parse manifest ({})", path, e));
+        }
+        return;
+    }
 
-    // Build final modular alias section in a sorted order.
-    let mut new_alias_block = String::new();
-    new_alias_block.push_str(&format!("{}\n", MODULAR_ALIAS_START_MARKER));
+    let Ok(file) = fs::File::open(path) else {
+        problems.push(format!("{}: could not be opened", path));
+        return;
+    };
 
-    let mut sorted_aliases: Vec<String> = new_aliases_from_file.clone().into_iter().map(|(alias_name, _)| (alias_name)).collect();
-    sorted_aliases.sort_by(|a, b| a.cmp(b));
-    for alias_line in sorted_aliases {
-        new_alias_block.push_str(&format!("alias {}={}", alias_line, new_aliases_from_file.get(&alias_line).unwrap().value));
-        new_alias_block.push('\n');
+    for (line_no, line_result) in io::BufReader::new(file).lines().enumerate() {
+        let Ok(line) = line_result else {
+            problems.push(format!("{}:{}: could not be read", path, line_no + 1));
+            continue;
+        };
+        let trimmed_line = line.trim();
+
+        if trimmed_line.starts_with("alias ") && parse_alias_line(&line, AliasSource::Default).is_none() {
+            problems.push(format!("{}:{}: malformed alias line: {}", path, line_no + 1, trimmed_line));
+        } else if !trimmed_line.is_empty() && !trimmed_line.starts_with('#') && trimmed_line.contains('(') {
+            match parse_parameterized_line(&line, AliasSource::Default) {
+                None => problems.push(format!("{}:{}: malformed parameterized alias line: {}", path, line_no + 1, trimmed_line)),
+                Some((alias_name, alias)) => {
+                    if let Some(params) = &alias.params {
+                        if let Err(e) = validate_params(&alias_name, params, &alias.value) {
+                            problems.push(format!("{}:{}: {}", path, line_no + 1, e));
+                        }
+                    }
+                },
+            }
+        } else if let Some(include_name) = trimmed_line.strip_prefix("#include:") {
+            let include_path = config.alias_dir.join(include_name.trim());
+            if !include_path.exists() {
+                problems.push(format!("{}:{}: included file not found: {}", path, line_no + 1, include_path.display()));
+            }
+        }
     }
-    new_alias_block.push_str(&format!("{}\n", MODULAR_ALIAS_END_MARKER));
+}
 
-    // Remove the existing modular alias section (if any) and append the new section.
-    let new_bashrc = remove_section(&base_content, MODULAR_ALIAS_START_MARKER, MODULAR_ALIAS_END_MARKER);
-    let final_bashrc = format!("{}{}", new_bashrc, new_alias_block);
-    fs::write(BASHRC, final_bashrc).expect("Failed to fail properly, what a fail.");
+/// Deserializes a `.yml`/`.yaml`/`.toml` alias manifest into the same
+/// `HashMap<String, Alias>` shape the line-based parser produces.
+fn parse_manifest_file(path: &str) -> io::Result<HashMap<String, Alias>> {
+    let contents = fs::read_to_string(path)?;
+    let raw: HashMap<String, ManifestAliasEntry> = if path.ends_with(".toml") {
+        toml::from_str(&contents).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?
+    } else {
+        serde_yaml::from_str(&contents).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?
+    };
 
-    println!("Modular alias setup complete. Remember to source your .bashrc (e.g., 'source ~/.bashrc') to apply the changes.");
-    Ok(())
+    let mut map = HashMap::new();
+    for (alias_name, entry) in raw {
+        let alias = match entry {
+            ManifestAliasEntry::Simple(value) => Alias {
+                value,
+                source: AliasSource::Path(path.to_string()),
+                group: None,
+                params: None,
+                description: None,
+            },
+            ManifestAliasEntry::Detailed { value, body, args, group, description } => {
+                let body = body.or(value).unwrap_or_default();
+                if let Some(params) = &args {
+                    validate_params(&alias_name, params, &body)?;
+                }
+                Alias {
+                    value: body,
+                    source: AliasSource::Path(path.to_string()),
+                    group,
+                    params: args,
+                    description,
+                }
+            },
+        };
+        map.insert(alias_name, alias);
+    }
+    Ok(map)
 }
 
 /// Processes an alias file, inserting any lines starting with "alias " into the map.
-fn process_alias_file(path: &str, alias_map: &mut HashMap<String, Alias>, referenced_include_file_paths: &mut Vec<String>) -> io::Result<()> {
+/// `default_group` is the group aliases from this file fall back to when they
+/// don't carry their own (e.g. a manifest's `group:` field), derived from the
+/// immediate subdirectory of `aliases/` the file lives in, if any.
+fn process_alias_file(path: &str, alias_map: &mut HashMap<String, Alias>, referenced_include_file_paths: &mut Vec<String>, default_group: Option<&str>, config: &Config) -> io::Result<()> {
+    if path.ends_with(".yml") || path.ends_with(".yaml") || path.ends_with(".toml") {
+        let manifest_aliases = parse_manifest_file(path)?;
+        for (alias_name, mut alias) in manifest_aliases {
+            if alias.group.is_none() {
+                alias.group = default_group.map(|g| g.to_string());
+            }
+            cache_alias(alias_name, alias, alias_map, config).expect("Failed to insert alias.");
+        }
+        return Ok(());
+    }
+
     let file = fs::File::open(path).expect("Failed to fail properly, what a fail.");
     let reader = io::BufReader::new(file);
     for line_result in reader.lines() {
@@ -148,12 +701,15 @@ fn process_alias_file(path: &str, alias_map: &mut HashMap<String, Alias>, refere
         let trimmed_line = line.trim();
 
         if trimmed_line.starts_with("alias ") {
-            cache_incoming_aliases(&path, alias_map, &line).expect("Failed to insert alias.");
-        } else if trimmed_line.starts_with("#include:") {
-            let include_path = format!("{}/{}", ALIAS_DIR, trimmed_line[9..].trim());
+            cache_incoming_aliases(path, alias_map, &line, default_group, config).expect("Failed to insert alias.");
+        } else if !trimmed_line.is_empty() && !trimmed_line.starts_with('#') && trimmed_line.contains('(') {
+            cache_incoming_parameterized(path, alias_map, &line, default_group, config).expect("Failed to insert parameterized alias.");
+        } else if let Some(include_name) = trimmed_line.strip_prefix("#include:") {
+            let include_path = config.alias_dir.join(include_name.trim());
+            let include_path = include_path.to_str().expect("Failed to fail properly, what a fail.").to_string();
             match process_includes_file_references(&include_path, referenced_include_file_paths) {
                 Some(path) => {
-                    process_alias_file(&path, alias_map, referenced_include_file_paths).expect("Failed to process included file.");
+                    process_alias_file(&path, alias_map, referenced_include_file_paths, default_group, config).expect("Failed to process included file.");
                 },
                 None => {
                     continue;
@@ -167,14 +723,14 @@ fn process_alias_file(path: &str, alias_map: &mut HashMap<String, Alias>, refere
 fn process_includes_file_references(path: &str, referenced_include_file_paths: &mut Vec<String>) -> Option<String> {
     if Path::new(path).exists() {
         if referenced_include_file_paths.contains(&path.to_string()) {
-            return None;
+            None
         } else {
             referenced_include_file_paths.push(path.to_string());
-            return Some(path.to_string());
+            Some(path.to_string())
         }
     } else {
         eprintln!("Error: Included file not found: {}", path);
-        return None;
+        None
     }
 }
 
@@ -182,52 +738,173 @@ fn show_diff(alias: &str, old_val: Alias, new_val: Alias, conflict_type: &Confli
     let source_path = old_val.source.get_path();
     let dest_path = new_val.source.get_path();
     let mut source_name = source_path.to_string();
-    let mut dest_name = dest_path.to_string(); 
+    let mut dest_name = dest_path.to_string();
 
     if conflict_type == &ConflictType::WriteNew {
         source_name = "*** Alias loader sources ***".to_string();
         dest_name = "*** .bashrc file ***".to_string();
     };
-    
-    let colored_diff_str = format!(
-        "{}\n{}\n@@ -1 +1 @@\n {}\n {}\n",
-        style(format!("--- {}", source_name.clone())).red(),
-        style(format!("+++ {}", dest_name.clone())).green(),
-        style(format!("-  {}", &old_val.value)).red(),
-        style(format!("+  {}", &new_val.value)).green(),
-    );
 
+    let colored_diff_str = render_unified_diff(&source_name, &dest_name, &old_val.value, &new_val.value, 3);
 
     intro(style("Conflict!").on_cyan().black()).expect("Failed to display intro.");
-    cliclack::note(format!("Alias: {}", style(format!("{}", alias)).cyan()),
+    cliclack::note(format!("Alias: {}", style(alias.to_string()).cyan()),
     colored_diff_str,
     ).expect("msg");
 
 }
 
-fn conflict_resolver(alias_name: &str, current_val: &Alias, new_val: &Alias, conflict_type: ConflictType) -> Option<Alias> {    
+/// A single line-level edit between two texts.
+enum DiffOp {
+    Equal(String),
+    Removed(String),
+    Added(String),
+}
+
+/// Line-based LCS diff between `old_lines` and `new_lines`.
+fn diff_lines(old_lines: &[&str], new_lines: &[&str]) -> Vec<DiffOp> {
+    let n = old_lines.len();
+    let m = new_lines.len();
+    let mut lcs = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if old_lines[i] == new_lines[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut ops = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < n && j < m {
+        if old_lines[i] == new_lines[j] {
+            ops.push(DiffOp::Equal(old_lines[i].to_string()));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            ops.push(DiffOp::Removed(old_lines[i].to_string()));
+            i += 1;
+        } else {
+            ops.push(DiffOp::Added(new_lines[j].to_string()));
+            j += 1;
+        }
+    }
+    while i < n {
+        ops.push(DiffOp::Removed(old_lines[i].to_string()));
+        i += 1;
+    }
+    while j < m {
+        ops.push(DiffOp::Added(new_lines[j].to_string()));
+        j += 1;
+    }
+
+    ops
+}
+
+/// Renders a real multi-line unified diff between `old_content` and `new_content`,
+/// grouping changes into `@@ -a,b +c,d @@` hunks with `context` lines of surrounding
+/// text, colored the same red/green as the rest of the tool's conflict previews.
+/// Returns an empty string when the two texts are identical.
+fn render_unified_diff(old_label: &str, new_label: &str, old_content: &str, new_content: &str, context: usize) -> String {
+    let old_lines: Vec<&str> = old_content.lines().collect();
+    let new_lines: Vec<&str> = new_content.lines().collect();
+    let ops = diff_lines(&old_lines, &new_lines);
+
+    struct Entry {
+        op: DiffOp,
+        old_no: Option<usize>,
+        new_no: Option<usize>,
+    }
+
+    let mut entries = Vec::new();
+    let (mut old_no, mut new_no) = (0usize, 0usize);
+    for op in ops {
+        let entry = match &op {
+            DiffOp::Equal(_) => {
+                old_no += 1;
+                new_no += 1;
+                Entry { op, old_no: Some(old_no), new_no: Some(new_no) }
+            },
+            DiffOp::Removed(_) => {
+                old_no += 1;
+                Entry { op, old_no: Some(old_no), new_no: None }
+            },
+            DiffOp::Added(_) => {
+                new_no += 1;
+                Entry { op, old_no: None, new_no: Some(new_no) }
+            },
+        };
+        entries.push(entry);
+    }
+
+    let change_indices: Vec<usize> = entries
+        .iter()
+        .enumerate()
+        .filter(|(_, entry)| !matches!(entry.op, DiffOp::Equal(_)))
+        .map(|(index, _)| index)
+        .collect();
+
+    if change_indices.is_empty() {
+        return String::new();
+    }
+
+    let mut hunk_ranges: Vec<(usize, usize)> = Vec::new();
+    for index in change_indices {
+        let start = index.saturating_sub(context);
+        let end = (index + context).min(entries.len().saturating_sub(1));
+        match hunk_ranges.last_mut() {
+            Some(last) if start <= last.1 + 1 => last.1 = last.1.max(end),
+            _ => hunk_ranges.push((start, end)),
+        }
+    }
+
+    let mut out = String::new();
+    out.push_str(&format!("{}\n", style(format!("--- {}", old_label)).red()));
+    out.push_str(&format!("{}\n", style(format!("+++ {}", new_label)).green()));
+
+    for (start, end) in hunk_ranges {
+        let hunk = &entries[start..=end];
+        let old_start = hunk.iter().find_map(|entry| entry.old_no).unwrap_or(1).max(1);
+        let new_start = hunk.iter().find_map(|entry| entry.new_no).unwrap_or(1).max(1);
+        let old_count = hunk.iter().filter(|entry| entry.old_no.is_some()).count();
+        let new_count = hunk.iter().filter(|entry| entry.new_no.is_some()).count();
+
+        out.push_str(&format!("@@ -{},{} +{},{} @@\n", old_start, old_count, new_start, new_count));
+        for entry in hunk {
+            match &entry.op {
+                DiffOp::Equal(line) => out.push_str(&format!(" {}\n", line)),
+                DiffOp::Removed(line) => out.push_str(&format!("{}\n", style(format!("-{}", line)).red())),
+                DiffOp::Added(line) => out.push_str(&format!("{}\n", style(format!("+{}", line)).green())),
+            }
+        }
+    }
+
+    out
+}
+
+fn conflict_resolver(alias_name: &str, current_val: &Alias, new_val: &Alias, conflict_type: ConflictType, config: &Config) -> Option<Alias> {
     let new_val_value = new_val.value.clone();
 
     if conflict_type == ConflictType::WriteNew {
         cliclack::note("Current value", current_val.clone().value).expect("Failed to print note.");
         cliclack::note("Incoming cached value", new_val_value.clone()).expect("Failed to print note.");
     } else {
-        // cliclack::note(format!("Current cached value (from {:?})", current_val.source.get_path()), current_val.clone().value).expect("Failed to print note.");
-        // cliclack::note(format!("Incoming value (from {:?})", new_val.source.get_path()), new_val.value.clone()).expect("Failed to print note.");
         show_diff(alias_name, current_val.clone(), new_val.clone(), &conflict_type);
     }
 
     let conflict_resolution_heading = if conflict_type == ConflictType::WriteNew {
         format!("Replace the contents of the new alias {} with {}", style(alias_name).cyan().bold(), style(new_val_value.clone()).magenta())
-    } else {   
+    } else {
         format!("Replace the new alias {} on your `.bashrc` file with with {}", style(alias_name).cyan().bold(), style(new_val_value.clone()).magenta())
     };
-    
+
     let answer = select(conflict_resolution_heading)
         .item("y", "Yes", "Overwrite the existing alias. Default.")
         .item("n", "No", "Keep the current the value.")
         .item("i", "Ignore all", "Ignore all future conflicts.")
-        .item("a", "Accept all new", "Accept all new changes for subsequent conflicts.") 
+        .item("a", "Accept all new", "Accept all new changes for subsequent conflicts.")
         .filter_mode()
         .interact()
         .expect("Failed to get valid answer.");
@@ -235,69 +912,98 @@ fn conflict_resolver(alias_name: &str, current_val: &Alias, new_val: &Alias, con
     match answer.trim().to_lowercase().as_str() {
         "y" => {
             // Overwrite with incoming alias.
-            return Some(new_val.clone());
+            Some(new_val.clone())
         },
         "n" => {
             // Keep the existing alias.
-            return None;
+            None
         },
         "i" => {
             // Ignore all future conflicts.
-            unsafe {
-                IGNORE_FUTURE_CONFLICTS = true;
-            }
-            return None;
+            config.ignore_future_conflicts.set(true);
+            None
         },
         "a" => {
             // Accept all new changes for subsequent conflicts.
-            unsafe {
-                ACCEPT_ALL_NEW = true;
-            }
-            return Some(new_val.clone());
+            config.accept_all_new.set(true);
+            Some(new_val.clone())
         },
         _ => {
             // Default: Overwrite with incoming alias.
-            return Some(new_val.clone());
-
+            Some(new_val.clone())
         }
     }
 }
 
 /// Inserts an alias definition into the map; if a duplicate exists, prompts the user in interactive mode.
-fn cache_incoming_aliases(path: &str, alias_map: &mut HashMap<String, Alias>, alias_line: &str) -> io::Result<()> {
-    if let Some((alias_name, incoming_value)) = parse_alias_line(alias_line, AliasSource::Path(path.to_string())) {
-        // Check if alias already exists.
-        if let Some(pre_cached_value) = alias_map.get(&alias_name) {
-            if pre_cached_value.value != incoming_value.value {
-                if unsafe { YOLO_MODE } {
+fn cache_incoming_aliases(path: &str, alias_map: &mut HashMap<String, Alias>, alias_line: &str, default_group: Option<&str>, config: &Config) -> io::Result<()> {
+    if let Some((alias_name, mut incoming_value)) = parse_alias_line(alias_line, AliasSource::Path(path.to_string())) {
+        incoming_value.group = default_group.map(|g| g.to_string());
+        cache_alias(alias_name, incoming_value, alias_map, config)?;
+    }
+    Ok(())
+}
+
+/// Inserts a parameterized alias definition (`name(args) = body`) into the map.
+fn cache_incoming_parameterized(path: &str, alias_map: &mut HashMap<String, Alias>, alias_line: &str, default_group: Option<&str>, config: &Config) -> io::Result<()> {
+    if let Some((alias_name, mut incoming_value)) = parse_parameterized_line(alias_line, AliasSource::Path(path.to_string())) {
+        if let Some(params) = &incoming_value.params {
+            validate_params(&alias_name, params, &incoming_value.value)?;
+        }
+        incoming_value.group = default_group.map(|g| g.to_string());
+        cache_alias(alias_name, incoming_value, alias_map, config)?;
+    }
+    Ok(())
+}
+
+/// Rejects group names that would break out of the `#--- BEGIN/END Modular
+/// Aliases [name] ---` marker format (e.g. embedding `] ---` or a newline),
+/// which would otherwise corrupt `extract_group_sections`' parsing on the next run.
+fn validate_group_name(name: &str) -> io::Result<()> {
+    if name.contains('\n') || name.contains("] ---") {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("invalid alias group name '{}': must not contain '] ---' or a newline", name),
+        ));
+    }
+    Ok(())
+}
+
+/// Inserts a single resolved alias into the map; if a duplicate exists, prompts the user in interactive mode.
+fn cache_alias(alias_name: String, incoming_value: Alias, alias_map: &mut HashMap<String, Alias>, config: &Config) -> io::Result<()> {
+    if let Some(group) = &incoming_value.group {
+        validate_group_name(group)?;
+    }
+
+    // Check if alias already exists.
+    if let Some(pre_cached_value) = alias_map.get(&alias_name) {
+        if pre_cached_value.value != incoming_value.value {
+            if config.yolo_mode.get() {
+                alias_map.insert(alias_name, incoming_value);
+                return Ok(());
+            } else {
+                // If resolution flags are already set, obey them.
+                if config.accept_all_new.get() {
                     alias_map.insert(alias_name, incoming_value);
                     return Ok(());
-                } else {
-                    // If global flags are already set, obey them.
-                    unsafe {
-                        if ACCEPT_ALL_NEW {
-                            alias_map.insert(alias_name, incoming_value);
-                            return Ok(());
-                        }
-                        if IGNORE_FUTURE_CONFLICTS {
-                            return Ok(());
-                        }
-                    }
+                }
+                if config.ignore_future_conflicts.get() {
+                    return Ok(());
+                }
 
-                    match conflict_resolver(&alias_name, &pre_cached_value, &incoming_value, ConflictType::CacheIncoming) {
-                        Some(resolved_value) => {
-                            alias_map.insert(alias_name, resolved_value);
-                        },
-                        None => {
-                            return Ok(());
-                        }
+                match conflict_resolver(&alias_name, pre_cached_value, &incoming_value, ConflictType::CacheIncoming, config) {
+                    Some(resolved_value) => {
+                        alias_map.insert(alias_name, resolved_value);
+                    },
+                    None => {
+                        return Ok(());
                     }
                 }
             }
-        } else {
-            // No conflict; insert the alias.
-            alias_map.insert(alias_name, incoming_value);
         }
+    } else {
+        // No conflict; insert the alias.
+        alias_map.insert(alias_name, incoming_value);
     }
     Ok(())
 }
@@ -305,26 +1011,56 @@ fn cache_incoming_aliases(path: &str, alias_map: &mut HashMap<String, Alias>, al
 /// Parses an alias line (starting with "alias ") to extract the alias name.
 fn parse_alias_line(line: &str, alias_source: AliasSource) -> Option<(String, Alias)> {
     let trimmed = line.trim();
-    if trimmed.starts_with("alias ") {
-        let without_prefix = &trimmed[6..];
-        return match without_prefix.split_once('=') {
-            Some((alias_name, alias_value)) => Some((alias_name.trim().to_string(), Alias { value: alias_value.trim().to_string(), source: alias_source })),
-            None => None
-        };
+    let without_prefix = trimmed.strip_prefix("alias ")?;
+    without_prefix.split_once('=').map(|(alias_name, alias_value)| {
+        (alias_name.trim().to_string(), Alias { value: alias_value.trim().to_string(), source: alias_source, group: None, params: None, description: None })
+    })
+}
+
+/// Parses a parameterized alias line, e.g. `gco(branch) = git checkout $branch`,
+/// into its name, declared positional parameters, and body.
+fn parse_parameterized_line(line: &str, alias_source: AliasSource) -> Option<(String, Alias)> {
+    let trimmed = line.trim();
+    let open_paren = trimmed.find('(')?;
+    let close_paren = open_paren + trimmed[open_paren..].find(')')?;
+    let name = trimmed[..open_paren].trim();
+    if name.is_empty() || name.contains(char::is_whitespace) {
+        return None;
     }
-    None
+
+    let args_str = trimmed[open_paren + 1..close_paren].trim();
+    let params: Vec<String> = if args_str.is_empty() {
+        Vec::new()
+    } else {
+        args_str.split(',').map(|param| param.trim().to_string()).collect()
+    };
+
+    let body = trimmed[close_paren + 1..].trim_start().strip_prefix('=')?.trim().to_string();
+
+    Some((name.to_string(), Alias {
+        value: body,
+        source: alias_source,
+        group: None,
+        params: Some(params),
+        description: None,
+    }))
 }
 
-/// Extracts the text between start_marker and end_marker from content.
-fn extract_section<'a>(content: &'a str, start_marker: &str, end_marker: &str) -> Option<&'a str> {
-    if let Some(start_index) = content.find(start_marker) {
-        let remainder = &content[start_index..];
-        if let Some(offset) = remainder.find(end_marker) {
-            let end_index = start_index + offset + end_marker.len();
-            return Some(&content[start_index..end_index]);
+/// Ensures every `$param` token referenced in `body` was declared in `params`,
+/// returning an error naming the offending alias and parameter otherwise.
+fn validate_params(alias_name: &str, params: &[String], body: &str) -> io::Result<()> {
+    let declared: std::collections::HashSet<&str> = params.iter().map(|param| param.as_str()).collect();
+    for token in body.split_whitespace() {
+        let Some(rest) = token.strip_prefix('$') else { continue };
+        let param_name: String = rest.chars().take_while(|c| c.is_alphanumeric() || *c == '_').collect();
+        if !param_name.is_empty() && !declared.contains(param_name.as_str()) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("parameterized alias '{}' references undeclared parameter '${}'", alias_name, param_name),
+            ));
         }
     }
-    None
+    Ok(())
 }
 
 /// Parses a modular alias section into a HashMap.
@@ -342,18 +1078,19 @@ fn parse_modular_aliases(section: &str, alias_source: AliasSource) -> HashMap<St
 fn compile_new_aliases(
     old: &HashMap<String, Alias>,
     new: &HashMap<String, Alias>,
+    config: &Config,
 ) -> io::Result<HashMap<String, Alias>> {
     let mut final_map = old.clone();
 
     for (alias_key, new_value) in new {
         if let Some(old_value) = old.get(alias_key) {
             if old_value.value != new_value.value {
-                if unsafe { YOLO_MODE } {
-                    if let Some(resolved_value) = conflict_resolver(alias_key, old_value, new_value, ConflictType::WriteNew) {
+                if config.yolo_mode.get() || config.accept_all_new.get() {
+                    final_map.insert(alias_key.to_string(), new_value.clone());
+                } else if !config.ignore_future_conflicts.get() {
+                    if let Some(resolved_value) = conflict_resolver(alias_key, old_value, new_value, ConflictType::WriteNew, config) {
                         final_map.insert(alias_key.to_string(), resolved_value);
                     }
-                } else {
-                    final_map.insert(alias_key.to_string(), new_value.clone());
                 }
             }
         } else {
@@ -363,20 +1100,289 @@ fn compile_new_aliases(
     Ok(final_map)
 }
 
+/// White (unvisited) aliases simply have no entry in the `colors` map.
+#[derive(Clone, Copy, PartialEq)]
+enum VisitColor {
+    Gray,
+    Black,
+}
+
+/// Returns the names of aliases referenced via `@name` tokens in an alias value.
+fn referenced_alias_names(value: &str) -> Vec<String> {
+    value
+        .split_whitespace()
+        .filter_map(|token| token.strip_prefix('@').map(|name| name.to_string()))
+        .collect()
+}
+
+/// Replaces every `@name` token in `value` with the already-resolved value of that alias.
+fn substitute_alias_refs(value: &str, resolved: &HashMap<String, String>) -> String {
+    value
+        .split_whitespace()
+        .map(|token| match token.strip_prefix('@') {
+            Some(name) => resolved.get(name).cloned().unwrap_or_else(|| token.to_string()),
+            None => token.to_string(),
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// Formats the cycle path (a -> b -> c -> a) starting from where `name`
+/// first appears in `stack`, assuming `name` has already been pushed onto it.
+fn format_cycle_path(stack: &[String], name: &str) -> String {
+    let cycle_start = stack.iter().position(|n| n == name).unwrap();
+    stack[cycle_start..].join(" -> ")
+}
+
+/// DFS with three-color marking over the alias reference graph: resolves `@name`
+/// references in topological order and returns an error naming the full cycle
+/// path (a->b->c->a) if a gray (on-stack) node is revisited, or the offending
+/// undefined reference, so callers can route either through the same
+/// backup-restore path as any other build failure instead of hard-exiting.
+fn visit_alias(
+    name: &str,
+    aliases: &HashMap<String, Alias>,
+    colors: &mut HashMap<String, VisitColor>,
+    resolved: &mut HashMap<String, String>,
+    stack: &mut Vec<String>,
+) -> io::Result<()> {
+    match colors.get(name) {
+        Some(VisitColor::Black) => return Ok(()),
+        Some(VisitColor::Gray) => {
+            stack.push(name.to_string());
+            let cycle_path = format_cycle_path(stack, name);
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("alias reference cycle detected: {}", cycle_path)));
+        },
+        _ => {},
+    }
+
+    let Some(alias) = aliases.get(name) else {
+        // Nothing to resolve for an alias that isn't part of this build; leave it be.
+        return Ok(());
+    };
+
+    colors.insert(name.to_string(), VisitColor::Gray);
+    stack.push(name.to_string());
+
+    for referenced_name in referenced_alias_names(&alias.value) {
+        if !aliases.contains_key(&referenced_name) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("alias '{}' references undefined alias '{}'.", name, referenced_name),
+            ));
+        }
+        visit_alias(&referenced_name, aliases, colors, resolved, stack)?;
+    }
+
+    let resolved_value = if referenced_alias_names(&alias.value).is_empty() {
+        alias.value.clone()
+    } else {
+        substitute_alias_refs(&alias.value, resolved)
+    };
+    resolved.insert(name.to_string(), resolved_value);
+    colors.insert(name.to_string(), VisitColor::Black);
+    stack.pop();
+    Ok(())
+}
+
+/// Resolves `@name` alias-to-alias references across the whole map before it's written out.
+fn resolve_alias_references(aliases: &HashMap<String, Alias>) -> io::Result<HashMap<String, Alias>> {
+    let mut colors: HashMap<String, VisitColor> = HashMap::new();
+    let mut resolved: HashMap<String, String> = HashMap::new();
+    let mut stack: Vec<String> = Vec::new();
+
+    for name in aliases.keys() {
+        visit_alias(name, aliases, &mut colors, &mut resolved, &mut stack)?;
+    }
+
+    let mut result = aliases.clone();
+    for (name, alias) in result.iter_mut() {
+        if let Some(resolved_value) = resolved.get(name) {
+            alias.value = resolved_value.clone();
+        }
+    }
+    Ok(result)
+}
+
 /// Repeatedly removes all occurrences of the section between start_marker and end_marker from content.
 fn remove_section(content: &str, start_marker: &str, end_marker: &str) -> String {
     let mut result = content.to_string();
-    loop {
-        if let Some(start) = result.find(start_marker) {
-            if let Some(end) = result[start..].find(end_marker) {
-                let end_index = start + end + end_marker.len();
-                result = result[..start-1].to_string() + &result[end_index..];
-            } else {
-                break;
-            }
+    while let Some(start) = result.find(start_marker) {
+        let Some(end) = result[start..].find(end_marker) else { break };
+        let end_index = start + end + end_marker.len();
+        // Also eat the newline right before the marker, but only if one is
+        // actually there (the block may start at byte 0, e.g. an empty .bashrc).
+        let trim_start = if start > 0 && result.as_bytes()[start - 1] == b'\n' {
+            start - 1
         } else {
-            break;
-        }
+            start
+        };
+        result = result[..trim_start].to_string() + &result[end_index..];
     }
     result
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn alias(value: &str) -> Alias {
+        Alias { value: value.to_string(), source: AliasSource::Default, group: None, params: None, description: None }
+    }
+
+    #[test]
+    fn referenced_alias_names_finds_at_tokens_only() {
+        assert_eq!(referenced_alias_names("@gs --all"), vec!["gs".to_string()]);
+        assert_eq!(referenced_alias_names("git status"), Vec::<String>::new());
+        assert_eq!(referenced_alias_names("@a @b"), vec!["a".to_string(), "b".to_string()]);
+    }
+
+    #[test]
+    fn substitute_alias_refs_replaces_known_names_and_leaves_others() {
+        let mut resolved = HashMap::new();
+        resolved.insert("gs".to_string(), "git status".to_string());
+        assert_eq!(substitute_alias_refs("@gs --all", &resolved), "git status --all");
+        assert_eq!(substitute_alias_refs("@missing --all", &resolved), "@missing --all");
+    }
+
+    #[test]
+    fn resolve_alias_references_expands_a_simple_reference() {
+        let mut aliases = HashMap::new();
+        aliases.insert("gs".to_string(), alias("git status"));
+        aliases.insert("ga".to_string(), alias("@gs --all"));
+
+        let resolved = resolve_alias_references(&aliases).unwrap();
+        assert_eq!(resolved.get("ga").unwrap().value, "git status --all");
+        assert_eq!(resolved.get("gs").unwrap().value, "git status");
+    }
+
+    #[test]
+    fn resolve_alias_references_expands_transitively() {
+        let mut aliases = HashMap::new();
+        aliases.insert("a".to_string(), alias("root"));
+        aliases.insert("b".to_string(), alias("@a mid"));
+        aliases.insert("c".to_string(), alias("@b leaf"));
+
+        let resolved = resolve_alias_references(&aliases).unwrap();
+        assert_eq!(resolved.get("c").unwrap().value, "root mid leaf");
+    }
+
+    #[test]
+    fn resolve_alias_references_leaves_alias_without_refs_untouched() {
+        let mut aliases = HashMap::new();
+        aliases.insert("ll".to_string(), alias("ls -la"));
+
+        let resolved = resolve_alias_references(&aliases).unwrap();
+        assert_eq!(resolved.get("ll").unwrap().value, "ls -la");
+    }
+
+    #[test]
+    fn format_cycle_path_starts_at_the_repeated_name() {
+        let stack = vec!["a".to_string(), "b".to_string(), "c".to_string(), "a".to_string()];
+        assert_eq!(format_cycle_path(&stack, "a"), "a -> b -> c -> a");
+    }
+
+    #[test]
+    fn render_unified_diff_is_empty_for_identical_content() {
+        assert_eq!(render_unified_diff("old", "new", "a\nb\nc\n", "a\nb\nc\n", 3), "");
+    }
+
+    #[test]
+    fn render_unified_diff_reports_correct_hunk_header() {
+        let diff = render_unified_diff("old", "new", "a\nb\nc\n", "a\nx\nc\n", 3);
+        let lines: Vec<&str> = diff.lines().collect();
+        assert_eq!(lines[0], "--- old");
+        assert_eq!(lines[1], "+++ new");
+        assert_eq!(lines[2], "@@ -1,3 +1,3 @@");
+    }
+
+    #[test]
+    fn render_unified_diff_handles_a_pure_append() {
+        let diff = render_unified_diff("old", "new", "a\nb\n", "a\nb\nc\n", 3);
+        let lines: Vec<&str> = diff.lines().collect();
+        assert_eq!(lines[2], "@@ -1,2 +1,3 @@");
+        assert!(lines.contains(&"+c"));
+    }
+
+    #[test]
+    fn diff_lines_matches_identical_text_as_all_equal() {
+        let lines = vec!["a", "b", "c"];
+        let ops = diff_lines(&lines, &lines);
+        assert_eq!(ops.len(), 3);
+        assert!(ops.iter().all(|op| matches!(op, DiffOp::Equal(_))));
+    }
+
+    #[test]
+    fn validate_params_accepts_only_declared_parameters() {
+        let params = vec!["branch".to_string()];
+        assert!(validate_params("gco", &params, "git checkout $branch").is_ok());
+        assert!(validate_params("gco", &params, "git checkout $other").is_err());
+    }
+
+    #[test]
+    fn parse_parameterized_line_extracts_name_params_and_body() {
+        let (name, alias) = parse_parameterized_line("gco(branch) = git checkout $branch", AliasSource::Default).unwrap();
+        assert_eq!(name, "gco");
+        assert_eq!(alias.params, Some(vec!["branch".to_string()]));
+        assert_eq!(alias.value, "git checkout $branch");
+    }
+
+    #[test]
+    fn parse_parameterized_line_rejects_lines_without_an_equals_sign() {
+        assert!(parse_parameterized_line("gco(branch) git checkout $branch", AliasSource::Default).is_none());
+    }
+
+    #[test]
+    fn render_alias_line_renders_a_plain_alias() {
+        assert_eq!(render_alias_line("ll", &alias("ls -la")), "alias ll=ls -la\n");
+    }
+
+    #[test]
+    fn render_alias_line_renders_a_parameterized_alias_as_a_function() {
+        let mut gco = alias("git checkout $branch");
+        gco.params = Some(vec!["branch".to_string()]);
+        let rendered = render_alias_line("gco", &gco);
+        assert_eq!(rendered, "gco() {\n    local branch=\"$1\"\n    git checkout $branch\n}\n");
+    }
+
+    #[test]
+    fn parse_manifest_file_reads_simple_and_detailed_yaml_entries() {
+        let path = std::env::temp_dir().join(format!("dotbasher_test_manifest_{:?}.yml", std::thread::current().id()));
+        fs::write(&path, "ll: ls -la\ngco:\n  body: git checkout $branch\n  args: [branch]\n  description: Check out a branch\n").unwrap();
+
+        let aliases = parse_manifest_file(path.to_str().unwrap()).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(aliases.get("ll").unwrap().value, "ls -la");
+        let gco = aliases.get("gco").unwrap();
+        assert_eq!(gco.value, "git checkout $branch");
+        assert_eq!(gco.params, Some(vec!["branch".to_string()]));
+        assert_eq!(gco.description.as_deref(), Some("Check out a branch"));
+    }
+
+    #[test]
+    fn parse_manifest_file_rejects_undeclared_parameters() {
+        let path = std::env::temp_dir().join(format!("dotbasher_test_manifest_bad_{:?}.yml", std::thread::current().id()));
+        fs::write(&path, "gco:\n  body: git checkout $other\n  args: [branch]\n").unwrap();
+
+        let result = parse_manifest_file(path.to_str().unwrap());
+        fs::remove_file(&path).unwrap();
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn diff_lines_reports_a_single_line_replacement() {
+        let ops = diff_lines(&["a", "b", "c"], &["a", "x", "c"]);
+        let removed: Vec<&str> = ops.iter().filter_map(|op| match op {
+            DiffOp::Removed(line) => Some(line.as_str()),
+            _ => None,
+        }).collect();
+        let added: Vec<&str> = ops.iter().filter_map(|op| match op {
+            DiffOp::Added(line) => Some(line.as_str()),
+            _ => None,
+        }).collect();
+        assert_eq!(removed, vec!["b"]);
+        assert_eq!(added, vec!["x"]);
+    }
+}